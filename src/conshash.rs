@@ -1,5 +1,4 @@
-use std::borrow::Borrow;
-use std::hash::Hash;
+use core::hash::Hash;
 
 /// Consistent hasher is a special kind of hashing such that when a hash table is resized,
 /// only `K/n` keys need to be remapped on average, where `K` is the number of keys,
@@ -12,8 +11,8 @@ pub trait ConsistentHasher<N: Sized> {
     fn capacity(&self) -> usize;
 
     /// Returns a reference to the node corresponding to the key.
-    fn get<Q: ?Sized>(&self, key: &Q) -> Option<&N>
-    where
-        Q: Hash + Eq,
-        N: Borrow<Q>;
+    ///
+    /// The key is hashed directly to pick a slot; `N` is never hashed or
+    /// compared, so unlike `HashMap::get` this doesn't require `N: Borrow<Q>`.
+    fn get<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<&N>;
 }
@@ -2,9 +2,22 @@
 //!
 //! [Maglev: A Fast and Reliable Software Network Load Balancer](https://static.googleusercontent.com/media/research.google.com/zh-CN//pubs/archive/44824.pdf)
 //!
+//! This crate is `no_std` when the default `std` feature is disabled, so it can
+//! run in constrained environments such as SGX enclaves; without `std` a
+//! [`Maglev`] must be built with an explicit hasher, since there is no default
+//! one to fall back on. Enabling the nightly-only `allocator_api` feature adds
+//! an allocator parameter to [`Maglev`] so its buffers can live in a
+//! caller-provided arena instead of the global allocator - see
+//! [`Maglev::with_capacity_and_hasher_in`].
+//!
 //! # Example
 //!
+//! This example needs the (default-on) `std` feature, since [`Maglev::new`]
+//! and [`Maglev::with_capacity`] rely on the default hasher.
+//!
 //! ```rust
+//! # #[cfg(feature = "std")]
+//! # fn main() {
 //! use maglev::*;
 //!
 //! let m = Maglev::new(vec!["Monday",
@@ -31,6 +44,9 @@
 //!
 //! assert_eq!(m["alice"], "Friday");
 //! assert_eq!(m["bob"], "Wednesday");
+//! # }
+//! # #[cfg(not(feature = "std"))]
+//! # fn main() {}
 //! ```
 //!
 //! Maglev use `std::collections::hash_map::DefaultHasher` by default,
@@ -58,8 +74,16 @@
 //!     assert_eq!(m["bob"], "Wednesday");
 //! }
 //! ```
+#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
+
+extern crate alloc;
+
 mod conshash;
 mod maglev;
 
 pub use crate::conshash::ConsistentHasher;
-pub use crate::maglev::Maglev;
+pub use crate::maglev::{DefaultHashBuilder, Maglev};
+
+#[cfg(all(feature = "rkyv", feature = "std"))]
+pub use crate::maglev::{ArchivedMaglev, MaglevArchive};
@@ -1,21 +1,105 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash, Hasher};
+use core::ops::Index;
+
+#[cfg(feature = "std")]
+use core::hash::BuildHasherDefault;
+#[cfg(feature = "std")]
+use core::iter;
+
+#[cfg(feature = "std")]
 use std::collections::hash_map::DefaultHasher;
-use std::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
-use std::iter;
-use std::ops::Index;
 
+#[cfg(feature = "std")]
 use primal::Sieve;
 
 use crate::conshash::ConsistentHasher;
 
-/// Maglev lookup table
+/// Satisfied by everything without `rayon`, and by `Sync` types with it.
+#[cfg(not(feature = "rayon"))]
+pub trait ParallelSafe {}
+#[cfg(not(feature = "rayon"))]
+impl<T> ParallelSafe for T {}
+
+#[cfg(feature = "rayon")]
+pub trait ParallelSafe: Sync {}
+#[cfg(feature = "rayon")]
+impl<T: Sync> ParallelSafe for T {}
+
+/// The hash builder `Maglev` falls back on when none is given.
+#[cfg(feature = "std")]
+pub type DefaultHashBuilder = BuildHasherDefault<DefaultHasher>;
+
+/// Inert placeholder filling `Maglev`'s default `S` under `no_std`, where
+/// there is no default hasher to offer.
+#[cfg(not(feature = "std"))]
+pub type DefaultHashBuilder = ();
+
+#[cfg(feature = "allocator_api")]
+use alloc::alloc::Global;
+#[cfg(feature = "allocator_api")]
+use core::alloc::Allocator;
+
+/// Smallest prime `>= from`, used to size the lookup table.
+#[cfg(feature = "std")]
+fn next_prime(from: usize) -> usize {
+    Sieve::new(from * 2).primes_from(from).next().unwrap()
+}
+
+#[cfg(not(feature = "std"))]
+fn next_prime(from: usize) -> usize {
+    let mut n = from.max(2);
+    while !is_prime(n) {
+        n += 1;
+    }
+    n
+}
+
+/// Deterministic primality check.
+// Manual remainder check, not `is_multiple_of`: that needs Rust 1.87+, newer
+// than this crate wants to require of `no_std` targets.
+#[allow(clippy::manual_is_multiple_of)]
+#[cfg(any(not(feature = "std"), feature = "serde"))]
+fn is_prime(n: usize) -> bool {
+    if n < 2 {
+        return false;
+    }
+    if n % 2 == 0 {
+        return n == 2;
+    }
+    let mut i = 3;
+    while i * i <= n {
+        if n % i == 0 {
+            return false;
+        }
+        i += 2;
+    }
+    true
+}
+
+/// Maglev lookup table.
+#[cfg(not(feature = "allocator_api"))]
 #[derive(Clone, Debug)]
-pub struct Maglev<N, S = BuildHasherDefault<DefaultHasher>> {
+pub struct Maglev<N, S = DefaultHashBuilder> {
     nodes: Vec<N>,
     lookup: Option<Vec<isize>>,
     hash_builder: S,
 }
 
-impl<N: Hash + Eq> Maglev<N, BuildHasherDefault<DefaultHasher>> {
+/// Maglev lookup table, generic over the allocator its buffers are drawn
+/// from. Requires the nightly-only `allocator_api` feature; see
+/// [`Maglev::with_capacity_and_hasher_in`].
+#[cfg(feature = "allocator_api")]
+#[derive(Clone, Debug)]
+pub struct Maglev<N, S = DefaultHashBuilder, A: Allocator = Global> {
+    nodes: Vec<N, A>,
+    lookup: Option<Vec<isize, A>>,
+    hash_builder: S,
+}
+
+#[cfg(feature = "std")]
+impl<N: Hash + Eq + ParallelSafe> Maglev<N, DefaultHashBuilder> {
     /// Creates a `Maglev` lookup table.
     pub fn new<I: IntoIterator<Item = N>>(nodes: I) -> Self {
         Maglev::with_capacity_and_hasher(nodes, 0, Default::default())
@@ -25,11 +109,43 @@ impl<N: Hash + Eq> Maglev<N, BuildHasherDefault<DefaultHasher>> {
     pub fn with_capacity<I: IntoIterator<Item = N>>(nodes: I, capacity: usize) -> Self {
         Maglev::with_capacity_and_hasher(nodes, capacity, Default::default())
     }
+
+    /// Creates a `Maglev` lookup table where each node receives a share of the
+    /// table proportional to its weight, instead of the equal share every other
+    /// constructor gives every node.
+    ///
+    /// If the weights sum to zero, there is no proportional share to derive,
+    /// so the table is built with an empty lookup - `get`/`Index` return
+    /// `None`/panic respectively, same as with an empty node list.
+    pub fn with_weights<I: IntoIterator<Item = (N, u32)>>(nodes: I) -> Self {
+        Maglev::with_capacity_and_weights_and_hasher(nodes, 0, Default::default())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<N: ParallelSafe> Maglev<N, DefaultHashBuilder> {
+    /// Creates a `Maglev` lookup table keyed off `key_fn(node)` rather than
+    /// `node` itself, so `N` need not implement `Hash + Eq` - useful for backend
+    /// structs that carry connection pools, health state, or other baggage that
+    /// isn't itself hashable, keying the table off a stable field such as an
+    /// address or ID instead.
+    pub fn with_key_fn<I, F, K>(nodes: I, key_fn: F) -> Self
+    where
+        I: IntoIterator<Item = N>,
+        F: Fn(&N) -> &K + ParallelSafe,
+        K: Hash + Eq + ?Sized,
+    {
+        Maglev::with_capacity_and_key_fn_and_hasher(nodes, 0, key_fn, Default::default())
+    }
 }
 
-impl<N: Hash + Eq, S: BuildHasher> Maglev<N, S> {
+impl<N, S: BuildHasher> Maglev<N, S> {
     /// Creates a `Maglev` lookup table which will use the given hash builder to hash keys.
-    pub fn with_hasher<I: IntoIterator<Item = N>>(nodes: I, hash_builder: S) -> Self {
+    pub fn with_hasher<I: IntoIterator<Item = N>>(nodes: I, hash_builder: S) -> Self
+    where
+        N: Hash + Eq + ParallelSafe,
+        S: ParallelSafe,
+    {
         Maglev::with_capacity_and_hasher(nodes, 0, hash_builder)
     }
 
@@ -38,7 +154,11 @@ impl<N: Hash + Eq, S: BuildHasher> Maglev<N, S> {
         nodes: I,
         capacity: usize,
         hash_builder: S,
-    ) -> Self {
+    ) -> Self
+    where
+        N: Hash + Eq + ParallelSafe,
+        S: ParallelSafe,
+    {
         let nodes = nodes.into_iter().collect::<Vec<_>>();
         let lookup = Self::populate(&nodes, capacity, &hash_builder);
 
@@ -49,6 +169,52 @@ impl<N: Hash + Eq, S: BuildHasher> Maglev<N, S> {
         }
     }
 
+    /// Creates a `Maglev` lookup table with the specified capacity and weights,
+    /// using hasher to hash the keys.
+    pub fn with_capacity_and_weights_and_hasher<I: IntoIterator<Item = (N, u32)>>(
+        nodes: I,
+        capacity: usize,
+        hash_builder: S,
+    ) -> Self
+    where
+        N: Hash + Eq + ParallelSafe,
+        S: ParallelSafe,
+    {
+        let (nodes, weights): (Vec<N>, Vec<u32>) = nodes.into_iter().unzip();
+        let lookup = Self::populate_weighted(&nodes, &weights, capacity, &hash_builder);
+
+        Maglev {
+            nodes,
+            lookup,
+            hash_builder,
+        }
+    }
+
+    /// Creates a `Maglev` lookup table with the specified capacity, keyed off
+    /// `key_fn(node)` and using `hash_builder` to hash keys.
+    pub fn with_capacity_and_key_fn_and_hasher<I, F, K>(
+        nodes: I,
+        capacity: usize,
+        key_fn: F,
+        hash_builder: S,
+    ) -> Self
+    where
+        I: IntoIterator<Item = N>,
+        F: Fn(&N) -> &K + ParallelSafe,
+        K: Hash + Eq + ?Sized,
+        N: ParallelSafe,
+        S: ParallelSafe,
+    {
+        let nodes = nodes.into_iter().collect::<Vec<_>>();
+        let lookup = Self::populate_by_key(&nodes, capacity, &hash_builder, &key_fn);
+
+        Maglev {
+            nodes,
+            lookup,
+            hash_builder,
+        }
+    }
+
     #[inline]
     fn hash_with_seed<Q: Hash + Eq + ?Sized>(key: &Q, seed: u32, hash_builder: &S) -> usize {
         let mut hasher = hash_builder.build_hasher();
@@ -57,36 +223,96 @@ impl<N: Hash + Eq, S: BuildHasher> Maglev<N, S> {
         hasher.finish() as usize
     }
 
-    fn populate(nodes: &[N], mut capacity: usize, hash_builder: &S) -> Option<Vec<isize>> {
+    #[inline]
+    fn permutation_for<K: Hash + Eq + ?Sized>(key: &K, m: usize, hash_builder: &S) -> Vec<usize> {
+        let offset = Self::hash_with_seed(key, 0xdead_babe, hash_builder) % m;
+        let skip = (Self::hash_with_seed(key, 0xdead_beef, hash_builder) % (m - 1)) + 1;
+
+        (0..m).map(|i| (offset + i * skip) % m).collect()
+    }
+
+    /// Builds the per-node permutation table, one row of length `m` per node,
+    /// keyed off `key_fn(node)` rather than `node` itself.
+    ///
+    /// Each row only depends on its own node, so with the `rayon` feature enabled
+    /// this fans the rows out across the global thread pool; the round-robin fill
+    /// in [`Maglev::populate`] stays sequential since it is order-dependent.
+    fn build_permutation<F, K>(nodes: &[N], m: usize, hash_builder: &S, key_fn: &F) -> Vec<Vec<usize>>
+    where
+        F: Fn(&N) -> &K + ParallelSafe,
+        K: Hash + Eq + ?Sized,
+        N: ParallelSafe,
+        S: ParallelSafe,
+    {
+        #[cfg(feature = "rayon")]
+        use rayon::prelude::*;
+
+        #[cfg(not(feature = "rayon"))]
+        let rows = nodes.iter();
+
+        #[cfg(feature = "rayon")]
+        let rows = nodes.par_iter();
+
+        rows.map(|node| Self::permutation_for(key_fn(node), m, hash_builder))
+            .collect()
+    }
+
+    fn populate(nodes: &[N], capacity: usize, hash_builder: &S) -> Option<Vec<isize>>
+    where
+        N: Hash + Eq + ParallelSafe,
+        S: ParallelSafe,
+    {
+        Self::populate_by_key(nodes, capacity, hash_builder, &|node: &N| node)
+    }
+
+    fn populate_by_key<F, K>(
+        nodes: &[N],
+        mut capacity: usize,
+        hash_builder: &S,
+        key_fn: &F,
+    ) -> Option<Vec<isize>>
+    where
+        F: Fn(&N) -> &K + ParallelSafe,
+        K: Hash + Eq + ?Sized,
+        N: ParallelSafe,
+        S: ParallelSafe,
+    {
         if nodes.is_empty() {
             return None;
         }
         if capacity == 0 {
             capacity = nodes.len() * 100
         }
-        let m = Sieve::new(capacity * 2)
-            .primes_from(capacity)
-            .next()
-            .unwrap();
-        let n = nodes.len();
+        let m = next_prime(capacity);
 
-        let permutation: Vec<Vec<usize>> = nodes
-            .iter()
-            .map(|node| {
-                let offset = Self::hash_with_seed(&node, 0xdead_babe, &hash_builder) % m;
-                let skip = (Self::hash_with_seed(&node, 0xdead_beef, &hash_builder) % (m - 1)) + 1;
+        let permutation = Self::build_permutation(nodes, m, hash_builder, key_fn);
+        let mut entry: Vec<isize> = vec![-1; m];
 
-                (0..m).map(|i| (offset + i * skip) % m).collect()
-            })
-            .collect();
+        Self::fill_round_robin(&permutation, &mut entry, None);
 
-        let mut next: Vec<usize> = vec![0; n];
-        let mut entry: Vec<isize> = vec![-1; m];
+        Some(entry)
+    }
 
+    /// Round-robin fill shared by [`Maglev::populate_by_key`],
+    /// [`Maglev::populate_weighted`], and [`Maglev::populate_in`]: claims
+    /// slots in `entry` for each node in permutation order, skipping slots
+    /// another node already holds, until `entry` is full or - when `target`
+    /// is given - every node has claimed its share.
+    fn fill_round_robin(permutation: &[Vec<usize>], entry: &mut [isize], target: Option<&[usize]>) {
+        let m = entry.len();
+        let n = permutation.len();
+        let mut next: Vec<usize> = vec![0; n];
+        let mut claimed: Vec<usize> = vec![0; n];
         let mut j = 0;
 
         while j < m {
             for i in 0..n {
+                if let Some(target) = target {
+                    if claimed[i] >= target[i] {
+                        continue;
+                    }
+                }
+
                 let mut c = permutation[i][next[i]];
 
                 while entry[c] >= 0 {
@@ -96,6 +322,7 @@ impl<N: Hash + Eq, S: BuildHasher> Maglev<N, S> {
 
                 entry[c] = i as isize;
                 next[i] += 1;
+                claimed[i] += 1;
                 j += 1;
 
                 if j == m {
@@ -103,20 +330,176 @@ impl<N: Hash + Eq, S: BuildHasher> Maglev<N, S> {
                 }
             }
         }
+    }
+
+    /// Same algorithm as [`Maglev::populate`], except each node claims at most
+    /// `target[i]` slots, where `target` is derived from `weights` so that a
+    /// node's share of the table is proportional to its weight.
+    fn populate_weighted(
+        nodes: &[N],
+        weights: &[u32],
+        mut capacity: usize,
+        hash_builder: &S,
+    ) -> Option<Vec<isize>>
+    where
+        N: Hash + Eq + ParallelSafe,
+        S: ParallelSafe,
+    {
+        if nodes.is_empty() {
+            return None;
+        }
+        if capacity == 0 {
+            capacity = nodes.len() * 100
+        }
+        let m = next_prime(capacity);
+        let n = nodes.len();
+
+        // Integer division keeps this no_std-friendly without `f64::floor`.
+        let total_weight: u64 = weights.iter().map(|&w| w as u64).sum();
+
+        // No proportional share to derive from all-zero weights.
+        if total_weight == 0 {
+            return None;
+        }
+        let scaled: Vec<u64> = weights.iter().map(|&w| w as u64 * m as u64).collect();
+        let mut target: Vec<usize> = scaled
+            .iter()
+            .map(|&s| (s / total_weight) as usize)
+            .collect();
+
+        // Largest remainder method: hands out the slots lost to flooring to
+        // the buckets with the biggest fractional part first, so `target`
+        // sums to exactly `m`.
+        let mut remainder = m - target.iter().sum::<usize>();
+        let mut by_fraction: Vec<usize> = (0..n).collect();
+        by_fraction.sort_by(|&a, &b| (scaled[b] % total_weight).cmp(&(scaled[a] % total_weight)));
+        for &i in by_fraction.iter() {
+            if remainder == 0 {
+                break;
+            }
+            target[i] += 1;
+            remainder -= 1;
+        }
+
+        let permutation = Self::build_permutation(nodes, m, hash_builder, &|node: &N| node);
+        let mut entry: Vec<isize> = vec![-1; m];
+
+        Self::fill_round_robin(&permutation, &mut entry, Some(&target));
 
         Some(entry)
     }
 }
 
-impl<N: Hash + Eq> iter::FromIterator<N> for Maglev<N, BuildHasherDefault<DefaultHasher>> {
+/// Creates a `Maglev` lookup table with the specified capacity, using
+/// `hash_builder` to hash keys. `alloc` is accepted for API parity with the
+/// (nightly-only) `allocator_api` build, but ignored: without that feature
+/// there is no stable way to route a `Vec`'s allocations through a
+/// caller-supplied allocator, so the table always uses the global one.
+#[cfg(not(feature = "allocator_api"))]
+impl<N, S: BuildHasher> Maglev<N, S> {
+    pub fn with_capacity_and_hasher_in<I: IntoIterator<Item = N>, A>(
+        nodes: I,
+        capacity: usize,
+        hash_builder: S,
+        _alloc: A,
+    ) -> Self
+    where
+        N: Hash + Eq + ParallelSafe,
+        S: ParallelSafe,
+    {
+        let nodes: Vec<N> = nodes.into_iter().collect();
+        let lookup = Self::populate(&nodes, capacity, &hash_builder);
+
+        Maglev {
+            nodes,
+            lookup,
+            hash_builder,
+        }
+    }
+}
+
+/// Threads a caller-provided allocator through the table's buffers, for
+/// constrained environments (e.g. SGX enclaves) or custom arenas. Requires
+/// the nightly-only `allocator_api` feature.
+#[cfg(feature = "allocator_api")]
+impl<N, S: BuildHasher, A: Allocator> Maglev<N, S, A> {
+    /// Creates a `Maglev` lookup table with the specified capacity, using
+    /// `hash_builder` to hash keys and allocating its buffers from `alloc`.
+    pub fn with_capacity_and_hasher_in<I: IntoIterator<Item = N>>(
+        nodes: I,
+        capacity: usize,
+        hash_builder: S,
+        alloc: A,
+    ) -> Self
+    where
+        N: Hash + Eq + ParallelSafe,
+        S: ParallelSafe,
+        A: Clone,
+    {
+        let mut node_vec = Vec::new_in(alloc.clone());
+        node_vec.extend(nodes);
+
+        let lookup = Self::populate_in(&node_vec, capacity, &hash_builder, alloc);
+
+        Maglev {
+            nodes: node_vec,
+            lookup,
+            hash_builder,
+        }
+    }
+
+    /// Same algorithm as [`Maglev::populate`], but allocating the final
+    /// lookup table from `alloc` instead of the global allocator. The
+    /// permutation table built along the way is scratch, discarded once this
+    /// returns, so it's built via [`Maglev::build_permutation`] (the same
+    /// helper `populate`/`populate_by_key`/`populate_weighted` use, rayon
+    /// fan-out included) rather than threading `alloc` through it too.
+    fn populate_in(
+        nodes: &[N],
+        mut capacity: usize,
+        hash_builder: &S,
+        alloc: A,
+    ) -> Option<Vec<isize, A>>
+    where
+        N: Hash + Eq + ParallelSafe,
+        S: ParallelSafe,
+        A: Clone,
+    {
+        if nodes.is_empty() {
+            return None;
+        }
+        if capacity == 0 {
+            capacity = nodes.len() * 100
+        }
+        let m = next_prime(capacity);
+
+        let permutation = Maglev::<N, S>::build_permutation(nodes, m, hash_builder, &|node: &N| node);
+
+        let mut entry: Vec<isize, A> = Vec::with_capacity_in(m, alloc);
+        entry.resize(m, -1);
+
+        Maglev::<N, S>::fill_round_robin(&permutation, &mut entry, None);
+
+        Some(entry)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<N: Hash + Eq + ParallelSafe> iter::FromIterator<N> for Maglev<N, DefaultHashBuilder> {
     fn from_iter<T: IntoIterator<Item = N>>(iter: T) -> Self {
         Maglev::new(iter)
     }
 }
 
+// Neither `get` nor `nodes`/`capacity` ever hash or compare `N` itself - the
+// hash comes from the query key (or, for a `with_key_fn` table, from
+// `key_fn(node)` at build time), and the lookup table already holds plain
+// indices into `nodes`. So unlike the constructors, this impl doesn't need
+// `N: Hash + Eq`, which lets tables built via `Maglev::with_key_fn` - whose
+// `N` need not be `Hash + Eq` - still be queried through `get`/`Index`.
+#[cfg(not(feature = "allocator_api"))]
 impl<N, S> ConsistentHasher<N> for Maglev<N, S>
 where
-    N: Hash + Eq,
     S: BuildHasher,
 {
     #[inline]
@@ -130,10 +513,7 @@ where
     }
 
     #[inline]
-    fn get<Q: ?Sized>(&self, key: &Q) -> Option<&N>
-    where
-        Q: Hash + Eq,
-    {
+    fn get<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<&N> {
         self.lookup.as_ref().map(|lookup| {
             let key = Self::hash_with_seed(key, 0xdead_babe, &self.hash_builder);
 
@@ -142,9 +522,9 @@ where
     }
 }
 
+#[cfg(not(feature = "allocator_api"))]
 impl<N, S, Q> Index<&Q> for Maglev<N, S>
 where
-    N: Hash + Eq,
     S: BuildHasher,
     Q: Hash + Eq + ?Sized,
 {
@@ -155,15 +535,239 @@ where
     }
 }
 
+// Mirrors the `not(allocator_api)` impls above, but generic over the
+// allocator too - without this, `Maglev<N, S>` only names `Maglev<N, S,
+// Global>`, so a table built via `with_capacity_and_hasher_in` with a
+// non-`Global` allocator would have no `get`/`capacity`/`nodes`/`Index` at
+// all.
+#[cfg(feature = "allocator_api")]
+impl<N, S, A: Allocator> ConsistentHasher<N> for Maglev<N, S, A>
+where
+    S: BuildHasher,
+{
+    #[inline]
+    fn nodes(&self) -> &[N] {
+        self.nodes.as_slice()
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.lookup.as_ref().map(|m| m.len()).unwrap_or_default()
+    }
+
+    #[inline]
+    fn get<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<&N> {
+        self.lookup.as_ref().map(|lookup| {
+            // `hash_with_seed` only touches `key` and `hash_builder`, not the
+            // allocator, so the `Global`-flavoured associated fn works for any `A`.
+            let key = Maglev::<N, S>::hash_with_seed(key, 0xdead_babe, &self.hash_builder);
+
+            &self.nodes[lookup[key % lookup.len()] as usize]
+        })
+    }
+}
+
+#[cfg(feature = "allocator_api")]
+impl<N, S, A: Allocator, Q> Index<&Q> for Maglev<N, S, A>
+where
+    S: BuildHasher,
+    Q: Hash + Eq + ?Sized,
+{
+    type Output = N;
+
+    fn index(&self, index: &Q) -> &Self::Output {
+        self.get(index).unwrap()
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_support {
+    use alloc::format;
+    use alloc::vec::Vec;
+
+    use serde::de::{Deserializer, Error as _};
+    use serde::ser::Serializer;
+    use serde::{Deserialize, Serialize};
+
+    use super::Maglev;
+
+    /// Borrowed shadow of [`Maglev`]'s persisted fields, used for serialization.
+    ///
+    /// The hash builder is not part of this: it is usually stateless
+    /// (`BuildHasherDefault`), so it is reconstructed via `Default` on load
+    /// rather than round-tripped.
+    #[derive(Serialize)]
+    struct MaglevRef<'a, N> {
+        nodes: &'a [N],
+        lookup: &'a Option<Vec<isize>>,
+    }
+
+    /// Owned shadow of [`Maglev`]'s persisted fields, used for deserialization.
+    #[derive(Deserialize)]
+    struct MaglevOwned<N> {
+        nodes: Vec<N>,
+        lookup: Option<Vec<isize>>,
+    }
+
+    impl<N, S> Serialize for Maglev<N, S>
+    where
+        N: Serialize,
+    {
+        fn serialize<Se: Serializer>(&self, serializer: Se) -> Result<Se::Ok, Se::Error> {
+            MaglevRef {
+                nodes: &self.nodes,
+                lookup: &self.lookup,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, N, S> Deserialize<'de> for Maglev<N, S>
+    where
+        N: Deserialize<'de>,
+        S: Default,
+    {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let MaglevOwned { nodes, lookup } = MaglevOwned::deserialize(deserializer)?;
+
+            if let Some(ref lookup) = lookup {
+                let m = lookup.len();
+
+                if !super::is_prime(m) {
+                    return Err(D::Error::custom(format!(
+                        "lookup table length {} is not prime",
+                        m
+                    )));
+                }
+
+                if lookup.iter().any(|&i| i < 0 || i as usize >= nodes.len()) {
+                    return Err(D::Error::custom(
+                        "lookup table contains an entry out of range for the node list",
+                    ));
+                }
+            }
+
+            Ok(Maglev {
+                nodes,
+                lookup,
+                hash_builder: S::default(),
+            })
+        }
+    }
+}
+
+#[cfg(all(feature = "rkyv", feature = "std"))]
+pub use self::rkyv_support::{ArchivedMaglev, MaglevArchive};
+
+#[cfg(all(feature = "rkyv", feature = "std"))]
+mod rkyv_support {
+    use alloc::vec::Vec;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    use rkyv::validation::CheckTypeError;
+    use rkyv::validation::validators::DefaultValidator;
+    use rkyv::{check_archived_root, Archive, CheckBytes, Deserialize, Serialize};
+
+    use super::Maglev;
+
+    /// Archivable snapshot of a [`Maglev`] table's `nodes` and `lookup`.
+    ///
+    /// The hash builder is not part of this: the seeds used to build the table
+    /// are fixed constants, so [`ArchivedMaglev::get`] rebuilds the default
+    /// hasher in place rather than archiving one. Build one with
+    /// [`Maglev::to_archive`], serialize it with `rkyv::to_bytes`, and later
+    /// access it either from trusted bytes via `rkyv::archived_root`, or from
+    /// an untrusted source (e.g. a memory-mapped file) via
+    /// [`ArchivedMaglev::from_archived_bytes`].
+    #[derive(Archive, Serialize, Deserialize)]
+    #[archive(archived = "ArchivedMaglev", check_bytes)]
+    pub struct MaglevArchive<N> {
+        nodes: Vec<N>,
+        lookup: Vec<isize>,
+    }
+
+    impl<N> Maglev<N, super::DefaultHashBuilder> {
+        /// Builds an archivable snapshot of this table, dropping the hash builder.
+        ///
+        /// Only available for tables built with the default hasher: since
+        /// [`ArchivedMaglev::get`] always rehashes queries with `DefaultHasher`,
+        /// archiving a table built with a custom hasher (e.g. via
+        /// [`Maglev::with_hasher`]) would silently look up the wrong slot.
+        pub fn to_archive(&self) -> MaglevArchive<N>
+        where
+            N: Clone,
+        {
+            MaglevArchive {
+                nodes: self.nodes.clone(),
+                lookup: self.lookup.clone().unwrap_or_default(),
+            }
+        }
+    }
+
+    impl<N> ArchivedMaglev<N>
+    where
+        N: Archive,
+    {
+        /// Returns a reference to the archived node corresponding to `key`,
+        /// hashing it with the same seed [`ConsistentHasher::get`](crate::ConsistentHasher::get) uses.
+        #[inline]
+        pub fn get<Q>(&self, key: &Q) -> Option<&N::Archived>
+        where
+            Q: Hash + Eq + ?Sized,
+        {
+            if self.lookup.is_empty() {
+                return None;
+            }
+
+            let mut hasher = DefaultHasher::new();
+            hasher.write_u32(0xdead_babe);
+            key.hash(&mut hasher);
+            let key = hasher.finish() as usize;
+
+            let index = self.lookup[key % self.lookup.len()];
+
+            if index < 0 {
+                None
+            } else {
+                Some(&self.nodes[index as usize])
+            }
+        }
+    }
+
+    impl<N> ArchivedMaglev<N>
+    where
+        N: Archive,
+    {
+        /// Validates `bytes` as an archived [`MaglevArchive`] before handing
+        /// back a reference into it.
+        ///
+        /// Use this instead of `rkyv::archived_root` for bytes from an
+        /// untrusted source (e.g. a memory-mapped file), since
+        /// `archived_root` trusts the bytes unchecked and a corrupted or
+        /// truncated file can make it read out of bounds.
+        pub fn from_archived_bytes<'a>(
+            bytes: &'a [u8],
+        ) -> Result<&'a Self, CheckTypeError<Self, DefaultValidator<'a>>>
+        where
+            Self: CheckBytes<DefaultValidator<'a>>,
+        {
+            check_archived_root::<MaglevArchive<N>>(bytes)
+        }
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
+    #[cfg(any(feature = "std", feature = "serde", feature = "rkyv"))]
+    use alloc::string::String;
+
     use fasthash::spooky::Hash128;
 
     use super::*;
     use crate::conshash::ConsistentHasher;
 
-    include!(concat!(env!("OUT_DIR"), "/skeptic-tests.rs"));
-
+    #[cfg(feature = "std")]
     #[test]
     fn test_maglev() {
         let m = Maglev::new(vec![
@@ -314,6 +918,22 @@ pub mod tests {
         assert_eq!(m["bob"], "Sunday");
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_maglev_with_many_nodes_fills_every_slot() {
+        // Exercises build_permutation's per-node rows across a node count large
+        // enough to matter if the rayon feature is enabled; the fill itself is
+        // still sequential, so every slot must end up claimed either way.
+        let nodes: Vec<String> = (0..64).map(|i| format!("node-{}", i)).collect();
+        let m = Maglev::with_capacity(nodes, 1_009);
+
+        let lookup = m.lookup.as_ref().unwrap();
+
+        assert_eq!(lookup.len(), 1_009);
+        assert!(lookup.iter().all(|&n| n >= 0 && (n as usize) < m.nodes.len()));
+    }
+
+    #[cfg(feature = "std")]
     #[test]
     fn test_maglev_with_empty_list() {
         let m = Maglev::<&str, _>::new(None);
@@ -323,4 +943,206 @@ pub mod tests {
 
         assert_eq!(m.get("alice"), None);
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_maglev_with_key_fn_is_queryable() {
+        // `f64` isn't `Hash + Eq`, so `Backend` can only be used with
+        // `with_key_fn` - `get`/`Index` must not require `N: Hash + Eq`.
+        struct Backend {
+            id: u32,
+            load: f64,
+        }
+
+        let backends = vec![
+            Backend { id: 1, load: 0.1 },
+            Backend { id: 2, load: 0.2 },
+            Backend { id: 3, load: 0.3 },
+        ];
+
+        let m = Maglev::with_key_fn(backends, |b: &Backend| &b.id);
+
+        assert_eq!(m.nodes().len(), 3);
+        assert_eq!(m.get("alice").unwrap().id, m["alice"].id);
+        assert_eq!(m["alice"].load, m.nodes()[m["alice"].id as usize - 1].load);
+    }
+
+    #[cfg(all(feature = "serde", feature = "std"))]
+    #[test]
+    fn test_maglev_serde_round_trips() {
+        let m = Maglev::new(vec![
+            "Monday".to_string(),
+            "Tuesday".to_string(),
+            "Wednesday".to_string(),
+        ]);
+
+        let json = serde_json::to_string(&m).unwrap();
+        let restored: Maglev<String, DefaultHashBuilder> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.nodes().len(), m.nodes().len());
+        assert_eq!(
+            restored.get("alice").map(|n| n.as_str()),
+            m.get("alice").map(|n| n.as_str())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_maglev_serde_rejects_non_prime_lookup_length() {
+        let json = r#"{"nodes":["a","b"],"lookup":[0,1,0,1]}"#;
+
+        let result: Result<Maglev<String, DefaultHashBuilder>, _> = serde_json::from_str(json);
+
+        assert!(result.is_err());
+    }
+
+    #[cfg(all(feature = "rkyv", feature = "std"))]
+    #[test]
+    fn test_maglev_to_archive_round_trips() {
+        let m = Maglev::new(vec![
+            "Monday".to_string(),
+            "Tuesday".to_string(),
+            "Wednesday".to_string(),
+        ]);
+
+        let archive = m.to_archive();
+        let bytes = rkyv::to_bytes::<_, 256>(&archive).expect("archive serializes");
+        let archived = unsafe { rkyv::archived_root::<MaglevArchive<String>>(&bytes) };
+
+        assert_eq!(
+            archived.get("alice").unwrap().as_str(),
+            m.get("alice").unwrap().as_str()
+        );
+        assert_eq!(
+            archived.get("bob").unwrap().as_str(),
+            m.get("bob").unwrap().as_str()
+        );
+    }
+
+    #[cfg(all(feature = "rkyv", feature = "std"))]
+    #[test]
+    fn test_maglev_from_archived_bytes_validates() {
+        let m = Maglev::new(vec![
+            "Monday".to_string(),
+            "Tuesday".to_string(),
+            "Wednesday".to_string(),
+        ]);
+
+        let bytes = rkyv::to_bytes::<_, 256>(&m.to_archive()).expect("archive serializes");
+        let archived =
+            ArchivedMaglev::<String>::from_archived_bytes(&bytes).expect("bytes are valid");
+
+        assert_eq!(
+            archived.get("alice").unwrap().as_str(),
+            m.get("alice").unwrap().as_str()
+        );
+
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(ArchivedMaglev::<String>::from_archived_bytes(truncated).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_maglev_with_weights_capacity_not_a_multiple_of_node_count() {
+        // Equal weights spread across a capacity that doesn't divide evenly.
+        let pairs: Vec<(&str, u32)> = vec![
+            ("a", 1),
+            ("b", 1),
+            ("c", 1),
+            ("d", 1),
+            ("e", 1),
+            ("f", 1),
+            ("g", 1),
+            ("h", 1),
+        ];
+
+        let m = Maglev::with_capacity_and_weights_and_hasher(pairs, 12, DefaultHashBuilder::default());
+
+        assert_eq!(m.nodes.len(), 8);
+        assert!(m.lookup.as_ref().unwrap().len() >= 12);
+        assert!(m
+            .lookup
+            .as_ref()
+            .unwrap()
+            .iter()
+            .all(|&n| n >= 0 && (n as usize) < m.nodes.len()));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_maglev_with_weights_all_zero_has_no_lookup() {
+        // No proportional share to derive, so the table has nodes but no lookup.
+        let pairs: Vec<(&str, u32)> = vec![("a", 0), ("b", 0), ("c", 0)];
+
+        let m = Maglev::with_weights(pairs);
+
+        assert_eq!(m.nodes.len(), 3);
+        assert!(m.lookup.is_none());
+        assert_eq!(m.get("alice"), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_maglev_with_weights_gives_proportional_share() {
+        // A 3:1 weight ratio should send roughly 3:1 as many keys to "heavy"
+        // as to "light" - the one behavior the whole weighted-nodes feature
+        // is for.
+        let pairs: Vec<(&str, u32)> = vec![("heavy", 3), ("light", 1)];
+
+        let m = Maglev::with_weights(pairs);
+
+        let (mut heavy_hits, mut light_hits) = (0u32, 0u32);
+        for i in 0..10_000 {
+            match *m.get(&i).unwrap() {
+                "heavy" => heavy_hits += 1,
+                "light" => light_hits += 1,
+                other => unreachable!("unexpected node {}", other),
+            }
+        }
+
+        let ratio = f64::from(heavy_hits) / f64::from(light_hits);
+        assert!(
+            (2.5..=3.5).contains(&ratio),
+            "expected ~3:1 split, got {}:{} (ratio {})",
+            heavy_hits,
+            light_hits,
+            ratio
+        );
+    }
+
+    #[cfg(all(feature = "std", not(feature = "allocator_api")))]
+    #[test]
+    fn test_maglev_with_capacity_and_hasher_in_is_queryable_without_allocator_api() {
+        // This is the build every consumer gets unless they opt into the
+        // nightly-only `allocator_api` feature, so it needs its own coverage
+        // rather than relying on the allocator_api-gated test below.
+        let m = Maglev::with_capacity_and_hasher_in(
+            vec!["Monday", "Tuesday", "Wednesday"],
+            0,
+            DefaultHashBuilder::default(),
+            (),
+        );
+
+        assert_eq!(m.capacity(), m.lookup.as_ref().unwrap().len());
+        assert_eq!(m.get("alice").unwrap(), &m["alice"]);
+    }
+
+    #[cfg(all(feature = "allocator_api", feature = "std"))]
+    #[test]
+    fn test_maglev_with_capacity_and_hasher_in_is_queryable() {
+        // Built with a concrete non-`Global` allocator, so this only compiles
+        // and passes if `ConsistentHasher`/`Index` are implemented generically
+        // over `A` rather than just for `Maglev<N, S, Global>`.
+        use std::alloc::System;
+
+        let m = Maglev::with_capacity_and_hasher_in(
+            vec!["Monday", "Tuesday", "Wednesday"],
+            0,
+            DefaultHashBuilder::default(),
+            System,
+        );
+
+        assert_eq!(m.capacity(), m.lookup.as_ref().unwrap().len());
+        assert_eq!(m.get("alice").unwrap(), &m["alice"]);
+    }
 }